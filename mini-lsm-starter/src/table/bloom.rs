@@ -0,0 +1,121 @@
+use anyhow::{bail, Result};
+use bytes::{BufMut, Bytes};
+
+/// A bloom filter over a set of 32-bit key hashes, built with the standard
+/// double-hashing trick so only one hash per key needs to be carried around.
+pub struct Bloom {
+    /// The bitmap backing the filter.
+    filter: Bytes,
+    /// Number of hash probes per key.
+    k: u8,
+}
+
+impl Bloom {
+    /// Decode a bloom filter previously written by [`Bloom::encode`]. The last byte holds `k`,
+    /// everything before it is the raw bitmap.
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        if buf.is_empty() {
+            bail!("corrupted bloom filter: empty buffer");
+        }
+        let k = buf[buf.len() - 1];
+        let filter = Bytes::copy_from_slice(&buf[..buf.len() - 1]);
+        Ok(Self { filter, k })
+    }
+
+    /// Encode the bloom filter as the raw bitmap followed by a trailing byte storing `k`.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.put_slice(&self.filter);
+        buf.put_u8(self.k);
+    }
+
+    /// Build a bloom filter sized for `keys.len()` entries at `bits_per_key` bits per key.
+    pub fn build_from_key_hashes(keys: &[u32], bits_per_key: usize) -> Self {
+        let k = ((bits_per_key as f64 * 0.69).round() as u32).max(1);
+
+        let nbits = (keys.len() * bits_per_key).max(8);
+        let nbytes = (nbits + 7) / 8;
+        let nbits = nbytes * 8;
+
+        let mut filter = vec![0u8; nbytes];
+        for &h in keys {
+            let mut h = h;
+            let delta = (h >> 17) | (h << 15);
+            for _ in 0..k {
+                let bit_pos = (h as usize) % nbits;
+                filter[bit_pos / 8] |= 1 << (bit_pos % 8);
+                h = h.wrapping_add(delta);
+            }
+        }
+
+        Self {
+            filter: filter.into(),
+            k: k as u8,
+        }
+    }
+
+    /// Returns `false` only if `h` is definitely not a member of the set the filter was built
+    /// from; `true` means "maybe present".
+    pub fn may_contain(&self, h: u32) -> bool {
+        if self.filter.is_empty() {
+            return false;
+        }
+        let nbits = self.filter.len() * 8;
+        let mut h = h;
+        let delta = (h >> 17) | (h << 15);
+        for _ in 0..self.k {
+            let bit_pos = (h as usize) % nbits;
+            if self.filter[bit_pos / 8] & (1 << (bit_pos % 8)) == 0 {
+                return false;
+            }
+            h = h.wrapping_add(delta);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bloom;
+
+    fn hash(key: &[u8]) -> u32 {
+        farmhash::hash32(key)
+    }
+
+    #[test]
+    fn no_false_negatives() {
+        let keys: Vec<u32> = (0..1000).map(|i| hash(format!("key_{i}").as_bytes())).collect();
+        let bloom = Bloom::build_from_key_hashes(&keys, 10);
+        for &h in &keys {
+            assert!(bloom.may_contain(h), "inserted key reported absent");
+        }
+    }
+
+    #[test]
+    fn false_positive_rate_is_reasonable() {
+        let keys: Vec<u32> = (0..10_000).map(|i| hash(format!("key_{i}").as_bytes())).collect();
+        let bloom = Bloom::build_from_key_hashes(&keys, 10);
+
+        let false_positives = (10_000..20_000)
+            .map(|i| hash(format!("key_{i}").as_bytes()))
+            .filter(|&h| bloom.may_contain(h))
+            .count();
+
+        // 10 bits/key targets ~1% FPR; leave generous headroom for flakiness.
+        assert!(
+            false_positives < 500,
+            "false positive rate too high: {false_positives}/10000"
+        );
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let keys: Vec<u32> = (0..100).map(|i| hash(format!("key_{i}").as_bytes())).collect();
+        let bloom = Bloom::build_from_key_hashes(&keys, 10);
+        let mut buf = Vec::new();
+        bloom.encode(&mut buf);
+        let decoded = Bloom::decode(&buf).unwrap();
+        for &h in &keys {
+            assert!(decoded.may_contain(h));
+        }
+    }
+}
@@ -2,42 +2,115 @@ use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::Result;
-use bytes::BufMut;
+use bytes::{BufMut, Bytes};
 
-use super::{BlockMeta, FileObject, SsTable};
+use super::sink::DataSink;
+use super::{BlockMeta, Bloom, CompressionCodec, OptimizeFor, SsTable};
 use crate::{block::BlockBuilder, lsm_storage::BlockCache};
+#[cfg(test)]
+use super::FileObject;
+
+/// Default number of bits allocated per key in the bloom filter.
+const BLOOM_BITS_PER_KEY: usize = 10;
 
 /// Builds an SSTable from key-value pairs.
 pub struct SsTableBuilder {
     pub(super) meta: Vec<BlockMeta>,
     ongoing_block_builder: BlockBuilder,
     first_key: Vec<u8>,
-    data: Vec<u8>,
+    data: DataSink,
     block_size: usize,
+    /// Hashes of every key added so far, used to build the bloom filter in `build`.
+    key_hashes: Vec<u32>,
+    /// Codec blocks are compressed with before being appended to `data`.
+    compression: CompressionCodec,
+    /// Whether a checksum is appended after every block's payload.
+    checksums: bool,
+    /// The first key ever added to the table.
+    table_first_key: Vec<u8>,
+    /// The most recent key added to the table.
+    last_key: Vec<u8>,
+    /// Total number of key-value pairs added so far.
+    num_entries: u32,
+    /// How the `FileObject` returned by `build` should back its reads.
+    optimize_for: OptimizeFor,
 }
 
 impl SsTableBuilder {
-    /// Create a builder based on target block size.
+    /// Create a builder based on target block size. Blocks are buffered in memory until `build`
+    /// is called; use `new_streaming` instead for tables too large to hold in memory at once.
     pub fn new(block_size: usize) -> Self {
         SsTableBuilder {
             meta: Vec::new(),
             ongoing_block_builder: BlockBuilder::new(block_size),
-            data: Vec::new(),
+            data: DataSink::buffered(),
             block_size,
             first_key: Vec::new(),
+            key_hashes: Vec::new(),
+            compression: CompressionCodec::None,
+            checksums: false,
+            table_first_key: Vec::new(),
+            last_key: Vec::new(),
+            num_entries: 0,
+            optimize_for: OptimizeFor::default(),
         }
     }
 
+    /// Create a builder that flushes each finished block straight to `path` as it's produced,
+    /// instead of buffering the whole table in memory. Block bytes waiting to be written are
+    /// bounded by the sink's flush threshold regardless of table size; block meta, stats and the
+    /// per-key hashes for the bloom filter are still kept in memory and grow with the table.
+    pub fn new_streaming(block_size: usize, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(SsTableBuilder {
+            meta: Vec::new(),
+            ongoing_block_builder: BlockBuilder::new(block_size),
+            data: DataSink::streaming(path.as_ref())?,
+            block_size,
+            first_key: Vec::new(),
+            key_hashes: Vec::new(),
+            compression: CompressionCodec::None,
+            checksums: false,
+            table_first_key: Vec::new(),
+            last_key: Vec::new(),
+            num_entries: 0,
+            optimize_for: OptimizeFor::default(),
+        })
+    }
+
+    /// Compress every block with `compression` before writing it out.
+    pub fn with_compression(mut self, compression: CompressionCodec) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Append a checksum after every block's payload so corruption can be detected on read.
+    pub fn with_checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Back the resulting `SsTable`'s reads per `optimize_for` (see `OptimizeFor`).
+    pub fn with_optimize_for(mut self, optimize_for: OptimizeFor) -> Self {
+        self.optimize_for = optimize_for;
+        self
+    }
+
     /// Adds a key-value pair to SSTable
     pub fn add(&mut self, key: &[u8], value: &[u8]) {
         if self.first_key.is_empty() {
             self.first_key = key.to_vec();
         }
+        if self.table_first_key.is_empty() {
+            self.table_first_key = key.to_vec();
+        }
+        self.last_key = key.to_vec();
+        self.num_entries += 1;
+        self.key_hashes.push(farmhash::hash32(key));
         // try to add to current block
         if self.ongoing_block_builder.add(key, value) {
             return;
         }
-        self.finish_block();
+        self.finish_block().expect("failed to flush finished block");
         debug_assert!(self.ongoing_block_builder.add(key, value));
         self.first_key = key.to_vec();
     }
@@ -56,41 +129,242 @@ impl SsTableBuilder {
         path: impl AsRef<Path>,
     ) -> Result<SsTable> {
         // finish building ongoing block
-        self.finish_block();
+        self.finish_block()?;
 
         let block_meta_offset = self.data.len();
 
-        let mut buffer = std::mem::take(&mut self.data);
+        let mut footer = Vec::new();
+        BlockMeta::encode_block_meta(&self.meta, &mut footer);
+        footer.put_u32(block_meta_offset as u32);
 
-        BlockMeta::encode_block_meta(&self.meta, &mut buffer);
+        let stats_offset = block_meta_offset + footer.len();
+        footer.put_u16(self.table_first_key.len() as u16);
+        footer.put_slice(&self.table_first_key);
+        footer.put_u16(self.last_key.len() as u16);
+        footer.put_slice(&self.last_key);
+        footer.put_u32(self.num_entries);
+        footer.put_u32(stats_offset as u32);
 
-        buffer.put_u32(block_meta_offset as u32);
+        let bloom_offset = block_meta_offset + footer.len();
+        let bloom = Bloom::build_from_key_hashes(&self.key_hashes, BLOOM_BITS_PER_KEY);
+        bloom.encode(&mut footer);
+        footer.put_u8(self.checksums as u8);
+        footer.put_u32(bloom_offset as u32);
+
+        self.data.write(&footer)?;
+        let file = self.data.finalize(path.as_ref(), self.optimize_for)?;
 
         Ok(SsTable {
-            file: FileObject::create(path.as_ref(), buffer).unwrap(),
+            file,
             block_metas: self.meta,
             block_meta_offset,
+            bloom: Some(bloom),
+            checksums_present: self.checksums,
+            verify_checksums: true,
+            first_key: Bytes::from(self.table_first_key),
+            last_key: Bytes::from(self.last_key),
+            num_entries: self.num_entries,
         })
     }
 
     /// utility function to finish building the current block
-    fn finish_block(&mut self) {
-        // builds new block meta and reset current first key in a single pass to prepare for new block
-        self.meta.push(BlockMeta {
-            offset: self.data.len(),
-            first_key: std::mem::take(&mut self.first_key).into(),
-        });
+    fn finish_block(&mut self) -> Result<()> {
         // reset block builder in self and take the one ready to build out of it to get mutable access.
         let ready_builder = std::mem::replace(
             &mut self.ongoing_block_builder,
             BlockBuilder::new(self.block_size),
         );
         let encoded_data = ready_builder.build().encode();
-        self.data.extend(encoded_data);
+        let uncompressed_len = encoded_data.len() as u32;
+        let payload = self.compression.compress(&encoded_data);
+
+        // builds new block meta and reset current first key in a single pass to prepare for new block
+        self.meta.push(BlockMeta {
+            offset: self.data.len(),
+            first_key: std::mem::take(&mut self.first_key).into(),
+            compression: self.compression,
+            uncompressed_len,
+        });
+        self.data.write(&payload)?;
+        if self.checksums {
+            self.data.write(&crc32c::crc32c(&payload).to_be_bytes())?;
+        }
+        Ok(())
     }
 
     #[cfg(test)]
     pub(crate) fn build_for_test(self, path: impl AsRef<Path>) -> Result<SsTable> {
         self.build(0, None, path)
     }
+
+    #[cfg(test)]
+    pub(crate) fn peak_buffered_bytes(&self) -> usize {
+        self.data.peak_buffered_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_table(dir: &tempfile::TempDir, name: &str, compression: CompressionCodec) -> SsTable {
+        let mut builder = SsTableBuilder::new(128).with_compression(compression);
+        for i in 0..200 {
+            builder.add(
+                format!("key_{i:05}").as_bytes(),
+                format!("value_{i:05}").as_bytes(),
+            );
+        }
+        builder.build_for_test(dir.path().join(name)).unwrap()
+    }
+
+    #[test]
+    fn compression_round_trips() {
+        let reference_dir = tempfile::tempdir().unwrap();
+        let reference = build_table(&reference_dir, "reference.sst", CompressionCodec::None);
+        for compression in [
+            CompressionCodec::None,
+            CompressionCodec::Lz4,
+            CompressionCodec::Zstd,
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let table = build_table(&dir, "table.sst", compression);
+            assert_eq!(table.num_of_blocks(), reference.num_of_blocks());
+            for block_idx in 0..table.num_of_blocks() {
+                let block = table.read_block(block_idx).unwrap();
+                assert!(!block.encode().is_empty());
+                let expected = reference.read_block(block_idx).unwrap();
+                assert_eq!(block.encode(), expected.encode());
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_compressed_block_errors_instead_of_panicking() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.sst");
+        let table = build_table(&dir, "table.sst", CompressionCodec::Lz4);
+        let meta = table.block_metas[0].clone();
+
+        let mut raw = std::fs::read(&path).unwrap();
+        // Flip a byte inside the first block's compressed payload, in place, so the file length
+        // (and therefore the footer) is untouched and only the LZ4 stream itself is corrupted.
+        raw[meta.offset] ^= 0xFF;
+        std::fs::write(&path, raw).unwrap();
+
+        // The footer is intact, so opening the table must succeed...
+        let corrupted =
+            SsTable::open(FileObject::open(&path, OptimizeFor::Cpu).unwrap(), true).unwrap();
+        // ...and decompressing the corrupted block must surface an error rather than panicking.
+        assert!(corrupted.read_block(0).is_err());
+    }
+
+    #[test]
+    fn persisted_range_and_count_match_inserted_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.sst");
+        let mut builder = SsTableBuilder::new(64);
+        // Small block size forces multiple blocks, exercising cross-block tracking.
+        for i in 0..500 {
+            builder.add(
+                format!("key_{i:05}").as_bytes(),
+                format!("value_{i:05}").as_bytes(),
+            );
+        }
+        let table = builder.build_for_test(&path).unwrap();
+        assert!(table.num_of_blocks() > 1, "expected keys to span multiple blocks");
+
+        assert_eq!(table.first_key(), b"key_00000".as_slice());
+        assert_eq!(table.last_key(), b"key_00499".as_slice());
+        assert_eq!(table.num_entries(), 500);
+
+        let reopened = SsTable::open(FileObject::open(&path, OptimizeFor::Cpu).unwrap(), true).unwrap();
+        assert_eq!(reopened.first_key(), b"key_00000".as_slice());
+        assert_eq!(reopened.last_key(), b"key_00499".as_slice());
+        assert_eq!(reopened.num_entries(), 500);
+    }
+
+    #[test]
+    fn streaming_builder_keeps_memory_bounded_and_reads_back_correctly() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.sst");
+
+        let mut builder = SsTableBuilder::new_streaming(4096, &path).unwrap();
+        let value = vec![0xAB; 1024];
+        let num_entries = 4096; // a few MB of key-value data overall
+        for i in 0..num_entries {
+            builder.add(format!("key_{i:08}").as_bytes(), &value);
+        }
+        let peak = builder.peak_buffered_bytes();
+        let table = builder.build_for_test(&path).unwrap();
+
+        // Peak buffered bytes must stay far below the resulting file size.
+        assert!(peak < 1024 * 1024, "peak buffered bytes too high: {peak}");
+        assert!(table.num_of_blocks() > 100);
+        assert_eq!(table.num_entries(), num_entries);
+
+        // Spot check a handful of blocks decode correctly end to end.
+        for block_idx in [0, table.num_of_blocks() / 2, table.num_of_blocks() - 1] {
+            assert!(!table.read_block(block_idx).unwrap().encode().is_empty());
+        }
+    }
+
+    #[test]
+    fn flipped_byte_is_caught_by_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("table.sst");
+        let mut builder = SsTableBuilder::new(128).with_checksums(true);
+        for i in 0..200 {
+            builder.add(
+                format!("key_{i:05}").as_bytes(),
+                format!("value_{i:05}").as_bytes(),
+            );
+        }
+        let table = builder.build_for_test(&path).unwrap();
+        let meta = table.block_metas[0].clone();
+
+        let mut raw = std::fs::read(&path).unwrap();
+        raw[meta.offset] ^= 0xFF;
+        std::fs::write(&path, raw).unwrap();
+
+        let reopened = SsTable::open(FileObject::open(&path, OptimizeFor::Cpu).unwrap(), true).unwrap();
+        assert!(reopened.read_block(0).is_err());
+    }
+
+    #[test]
+    fn owned_buffer_and_mmap_reads_agree() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let cpu_path = dir.path().join("cpu.sst");
+        let mut cpu_builder = SsTableBuilder::new(128).with_optimize_for(OptimizeFor::Cpu);
+        for i in 0..300 {
+            cpu_builder.add(
+                format!("key_{i:05}").as_bytes(),
+                format!("value_{i:05}").as_bytes(),
+            );
+        }
+        let cpu_table = cpu_builder.build_for_test(&cpu_path).unwrap();
+
+        let mmap_path = dir.path().join("mmap.sst");
+        let mut mmap_builder = SsTableBuilder::new(128).with_optimize_for(OptimizeFor::Memory);
+        for i in 0..300 {
+            mmap_builder.add(
+                format!("key_{i:05}").as_bytes(),
+                format!("value_{i:05}").as_bytes(),
+            );
+        }
+        // `build` drives the table's own `FileObject` through `DataSink::finalize`, so this
+        // exercises `FileObject::create`'s `OptimizeFor::Memory` branch (write then reopen as a
+        // mmap), not just `FileObject::open` on an already-written file.
+        let mmap_table = mmap_builder.build_for_test(&mmap_path).unwrap();
+
+        assert_eq!(cpu_table.num_of_blocks(), mmap_table.num_of_blocks());
+        for block_idx in 0..cpu_table.num_of_blocks() {
+            let cpu_block = cpu_table.read_block(block_idx).unwrap();
+            let mmap_block = mmap_table.read_block(block_idx).unwrap();
+            assert_eq!(cpu_block.encode(), mmap_block.encode());
+        }
+        assert_eq!(cpu_table.first_key(), mmap_table.first_key());
+        assert_eq!(cpu_table.last_key(), mmap_table.last_key());
+    }
 }
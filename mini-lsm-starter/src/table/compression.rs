@@ -0,0 +1,50 @@
+use anyhow::{bail, Result};
+
+/// Compression codec applied to a single block's encoded bytes before it is written to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionCodec {
+    /// Store the block as-is.
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl CompressionCodec {
+    pub(crate) fn as_u8(self) -> u8 {
+        match self {
+            CompressionCodec::None => 0,
+            CompressionCodec::Lz4 => 1,
+            CompressionCodec::Zstd => 2,
+        }
+    }
+
+    pub(crate) fn from_u8(v: u8) -> Result<Self> {
+        match v {
+            0 => Ok(CompressionCodec::None),
+            1 => Ok(CompressionCodec::Lz4),
+            2 => Ok(CompressionCodec::Zstd),
+            _ => bail!("unknown compression codec id: {v}"),
+        }
+    }
+
+    /// Compress `data`, returning it unchanged for `CompressionCodec::None`.
+    pub(crate) fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            CompressionCodec::None => data.to_vec(),
+            CompressionCodec::Lz4 => lz4_flex::compress(data),
+            CompressionCodec::Zstd => zstd::bulk::compress(data, 0).expect("zstd compression"),
+        }
+    }
+
+    /// Decompress `data`, which was produced by `compress` with the same codec and is known to
+    /// have been `uncompressed_len` bytes before compression.
+    pub(crate) fn decompress(self, data: &[u8], uncompressed_len: usize) -> Result<Vec<u8>> {
+        match self {
+            CompressionCodec::None => Ok(data.to_vec()),
+            CompressionCodec::Lz4 => lz4_flex::decompress(data, uncompressed_len)
+                .map_err(|e| anyhow::anyhow!("lz4 decompress failed: {e}")),
+            CompressionCodec::Zstd => zstd::bulk::decompress(data, uncompressed_len)
+                .map_err(|e| anyhow::anyhow!("zstd decompress failed: {e}")),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+#![allow(dead_code)] // REMOVE THIS LINE after fully implementing this functionality
+
+mod bloom;
+mod builder;
+mod compression;
+mod sink;
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use bytes::{Buf, BufMut, Bytes};
+
+pub use bloom::Bloom;
+pub use builder::SsTableBuilder;
+pub use compression::CompressionCodec;
+
+use crate::block::Block;
+
+/// The metadata for a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockMeta {
+    /// Offset of this data block (of the, possibly compressed, payload).
+    pub offset: usize,
+    /// The first key of the data block.
+    pub first_key: Bytes,
+    /// Codec the block payload was compressed with.
+    pub compression: CompressionCodec,
+    /// Size of the block once decompressed, needed to allocate the decompression buffer.
+    pub uncompressed_len: u32,
+}
+
+impl BlockMeta {
+    /// Encode block meta to a buffer.
+    pub fn encode_block_meta(block_meta: &[BlockMeta], buf: &mut Vec<u8>) {
+        for meta in block_meta {
+            buf.put_u32(meta.offset as u32);
+            buf.put_u16(meta.first_key.len() as u16);
+            buf.put_slice(&meta.first_key);
+            buf.put_u8(meta.compression.as_u8());
+            buf.put_u32(meta.uncompressed_len);
+        }
+    }
+
+    /// Decode block meta from a buffer.
+    pub fn decode_block_meta(mut buf: impl Buf) -> Result<Vec<BlockMeta>> {
+        let mut block_meta = Vec::new();
+        while buf.has_remaining() {
+            require_remaining(&buf, 4 + 2, "truncated block meta record header")?;
+            let offset = buf.get_u32() as usize;
+            let first_key_len = buf.get_u16() as usize;
+            require_remaining(
+                &buf,
+                first_key_len + 1 + 4,
+                "truncated block meta record body",
+            )?;
+            let first_key = buf.copy_to_bytes(first_key_len);
+            let compression = CompressionCodec::from_u8(buf.get_u8())?;
+            let uncompressed_len = buf.get_u32();
+            block_meta.push(BlockMeta {
+                offset,
+                first_key,
+                compression,
+                uncompressed_len,
+            });
+        }
+        Ok(block_meta)
+    }
+}
+
+/// Whether a reopened SSTable should be read fully into memory (favoring CPU/throughput) or
+/// memory-mapped (favoring a small resident-memory footprint). Useful when many SSTables are
+/// open at once and the block cache already holds the hot blocks, making a fully resident file
+/// wasteful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptimizeFor {
+    /// Read the whole file into an owned buffer once, so every subsequent read is a plain slice.
+    #[default]
+    Cpu,
+    /// Memory-map the file so pages are faulted in (and can be evicted) by the OS on demand.
+    Memory,
+}
+
+/// How a `FileObject` backs its bytes: either an owned in-memory buffer or a memory-mapped file.
+enum FileBacking {
+    Owned(Vec<u8>),
+    Mmap(memmap2::Mmap),
+}
+
+/// A file object, wrapping either an owned buffer or a memory-mapped file. Reads are plain slices
+/// in both cases; no per-read syscall is needed.
+pub struct FileObject {
+    backing: FileBacking,
+    size: u64,
+}
+
+impl FileObject {
+    fn bytes(&self) -> &[u8] {
+        match &self.backing {
+            FileBacking::Owned(buf) => buf,
+            FileBacking::Mmap(mmap) => mmap,
+        }
+    }
+
+    pub fn read(&self, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let start = offset as usize;
+        let end = start + len as usize;
+        let bytes = self.bytes();
+        if end > bytes.len() {
+            bail!("read past end of file: {end} > {}", bytes.len());
+        }
+        Ok(bytes[start..end].to_vec())
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Write `data` to `path` and hand back a `FileObject` backed according to `optimize_for`.
+    pub fn create(path: &Path, data: Vec<u8>, optimize_for: OptimizeFor) -> Result<Self> {
+        std::fs::write(path, &data)?;
+        File::open(path)?.sync_all()?;
+        let size = data.len() as u64;
+        match optimize_for {
+            // We already have the bytes in hand; no need to read the file back.
+            OptimizeFor::Cpu => Ok(FileObject {
+                backing: FileBacking::Owned(data),
+                size,
+            }),
+            OptimizeFor::Memory => {
+                drop(data);
+                Self::open(path, OptimizeFor::Memory)
+            }
+        }
+    }
+
+    /// Open an existing file, backed according to `optimize_for`.
+    pub fn open(path: &Path, optimize_for: OptimizeFor) -> Result<Self> {
+        let file = File::options().read(true).write(false).open(path)?;
+        let size = file.metadata()?.len();
+        let backing = match optimize_for {
+            OptimizeFor::Cpu => FileBacking::Owned(std::fs::read(path)?),
+            // Safety: the mapped file is only ever read by this process and is not truncated
+            // while mapped; `SsTableBuilder` never reopens a table it is still writing.
+            OptimizeFor::Memory => FileBacking::Mmap(unsafe { memmap2::Mmap::map(&file)? }),
+        };
+        Ok(FileObject { backing, size })
+    }
+}
+
+/// Subtract `b` from `a`, bailing with a descriptive error instead of panicking on underflow.
+/// Used throughout `SsTable::open` to validate footer offsets read from a possibly corrupted file.
+fn checked_sub(a: u64, b: u64, context: &str) -> Result<u64> {
+    a.checked_sub(b)
+        .ok_or_else(|| anyhow::anyhow!("corrupted SSTable footer: {context}"))
+}
+
+/// Bail instead of panicking if `buf` doesn't have at least `needed` bytes left. `bytes::Buf`'s
+/// `get_*`/`copy_to_bytes` methods panic on a short buffer, which a merely length-valid but
+/// otherwise corrupted footer section can easily trigger.
+fn require_remaining(buf: &impl Buf, needed: usize, context: &str) -> Result<()> {
+    if buf.remaining() < needed {
+        bail!("corrupted SSTable footer: {context}");
+    }
+    Ok(())
+}
+
+/// An SSTable, as produced by `SsTableBuilder::build`.
+pub struct SsTable {
+    pub(crate) file: FileObject,
+    pub(crate) block_metas: Vec<BlockMeta>,
+    pub(crate) block_meta_offset: usize,
+    /// Bloom filter covering every key added to this table, if one was built.
+    pub(crate) bloom: Option<Bloom>,
+    /// Whether blocks in this file were written with a trailing 4-byte checksum.
+    checksums_present: bool,
+    /// Whether `read_block` should verify the checksum (when present) rather than trust it.
+    verify_checksums: bool,
+    /// Smallest key in the table.
+    first_key: Bytes,
+    /// Largest key in the table.
+    last_key: Bytes,
+    /// Total number of key-value pairs in the table.
+    num_entries: u32,
+}
+
+impl SsTable {
+    /// Open an SSTable previously written by `SsTableBuilder::build`, loading its block meta and
+    /// bloom filter from the footer. `verify_checksums` controls whether block checksums (if the
+    /// file was built with them) are checked on every `read_block`.
+    pub fn open(file: FileObject, verify_checksums: bool) -> Result<Self> {
+        let len = file.size();
+        let bloom_offset_pos = checked_sub(len, 4, "file too short to contain a bloom offset")?;
+        let raw_bloom_offset = file.read(bloom_offset_pos, 4)?;
+        let bloom_offset = (&raw_bloom_offset[..]).get_u32() as u64;
+        let checksums_present_pos =
+            checked_sub(len, 5, "file too short to contain a checksums-present flag")?;
+        let raw_checksums_present = file.read(checksums_present_pos, 1)?;
+        let checksums_present = raw_checksums_present[0] != 0;
+        let bloom_len = checked_sub(
+            checksums_present_pos,
+            bloom_offset,
+            "bloom offset points past the checksums-present flag",
+        )?;
+        let raw_bloom = file.read(bloom_offset, bloom_len)?;
+        let bloom = Bloom::decode(&raw_bloom)?;
+
+        let stats_offset_pos = checked_sub(bloom_offset, 4, "bloom offset too small to be valid")?;
+        let raw_stats_offset = file.read(stats_offset_pos, 4)?;
+        let stats_offset = (&raw_stats_offset[..]).get_u32() as u64;
+        let stats_len = checked_sub(
+            stats_offset_pos,
+            stats_offset,
+            "stats offset points past the bloom filter",
+        )?;
+        let raw_stats = file.read(stats_offset, stats_len)?;
+        let mut stats_buf = &raw_stats[..];
+        require_remaining(&stats_buf, 2, "truncated stats section (first key length)")?;
+        let first_key_len = stats_buf.get_u16() as usize;
+        require_remaining(&stats_buf, first_key_len + 2, "truncated stats section (first key)")?;
+        let first_key = stats_buf.copy_to_bytes(first_key_len);
+        let last_key_len = stats_buf.get_u16() as usize;
+        require_remaining(&stats_buf, last_key_len + 4, "truncated stats section (last key)")?;
+        let last_key = stats_buf.copy_to_bytes(last_key_len);
+        let num_entries = stats_buf.get_u32();
+
+        let meta_offset_pos = checked_sub(stats_offset, 4, "stats offset too small to be valid")?;
+        let raw_meta_offset = file.read(meta_offset_pos, 4)?;
+        let block_meta_offset = (&raw_meta_offset[..]).get_u32() as u64;
+        let meta_len = checked_sub(
+            meta_offset_pos,
+            block_meta_offset,
+            "block meta offset points past the stats section",
+        )?;
+        let raw_meta = file.read(block_meta_offset, meta_len)?;
+        let block_metas = BlockMeta::decode_block_meta(&raw_meta[..])?;
+        Ok(Self {
+            file,
+            block_metas,
+            block_meta_offset: block_meta_offset as usize,
+            bloom: Some(bloom),
+            checksums_present,
+            verify_checksums,
+            first_key,
+            last_key,
+            num_entries,
+        })
+    }
+
+    /// Read and decompress a data block from disk, verifying its checksum if one is present and
+    /// `verify_checksums` was requested on open.
+    pub fn read_block(&self, block_idx: usize) -> Result<Arc<Block>> {
+        let meta = &self.block_metas[block_idx];
+        let offset = meta.offset;
+        let offset_end = self
+            .block_metas
+            .get(block_idx + 1)
+            .map_or(self.block_meta_offset, |next| next.offset);
+        let raw = self.file.read(offset as u64, (offset_end - offset) as u64)?;
+
+        let payload = if self.checksums_present {
+            if raw.len() < 4 {
+                bail!("block {block_idx} is too short to contain a checksum");
+            }
+            let split = raw.len() - 4;
+            let (payload, checksum_bytes) = raw.split_at(split);
+            if self.verify_checksums {
+                let expected = u32::from_be_bytes(checksum_bytes.try_into().unwrap());
+                let actual = crc32c::crc32c(payload);
+                if actual != expected {
+                    bail!(
+                        "checksum mismatch for block {block_idx}: expected {expected:#x}, got {actual:#x}"
+                    );
+                }
+            }
+            payload
+        } else {
+            &raw[..]
+        };
+
+        let encoded = meta
+            .compression
+            .decompress(payload, meta.uncompressed_len as usize)?;
+        Ok(Arc::new(Block::decode(&encoded)))
+    }
+
+    /// Find the block that may contain `key`.
+    pub fn find_block_idx(&self, key: &[u8]) -> usize {
+        self.block_metas
+            .partition_point(|meta| meta.first_key <= key)
+            .saturating_sub(1)
+    }
+
+    /// Returns `false` only if `key` is definitely absent from this table, letting callers skip
+    /// opening any block at all.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        match &self.bloom {
+            Some(bloom) => bloom.may_contain(farmhash::hash32(key)),
+            None => true,
+        }
+    }
+
+    /// Get number of data blocks.
+    pub fn num_of_blocks(&self) -> usize {
+        self.block_metas.len()
+    }
+
+    /// Smallest key stored in this table.
+    pub fn first_key(&self) -> &[u8] {
+        &self.first_key
+    }
+
+    /// Largest key stored in this table.
+    pub fn last_key(&self) -> &[u8] {
+        &self.last_key
+    }
+
+    /// Total number of key-value pairs stored in this table.
+    pub fn num_entries(&self) -> u32 {
+        self.num_entries
+    }
+}
@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::{FileObject, OptimizeFor};
+
+/// Above this many pending bytes, `DataSink::Streaming` flushes to disk rather than growing its
+/// write-behind buffer further.
+const STREAM_FLUSH_THRESHOLD: usize = 64 * 1024;
+
+/// Where `SsTableBuilder` accumulates block bytes before the table is finalized.
+///
+/// `Buffered` keeps everything in memory, which is simplest for small tables and tests.
+/// `Streaming` flushes finished blocks to an open file as they're produced, so memory use stays
+/// bounded regardless of the final table size (important during large compactions).
+pub(super) enum DataSink {
+    Buffered(Vec<u8>),
+    Streaming {
+        file: File,
+        path: PathBuf,
+        flushed_len: usize,
+        pending: Vec<u8>,
+        peak_pending_bytes: usize,
+    },
+}
+
+impl DataSink {
+    pub(super) fn buffered() -> Self {
+        DataSink::Buffered(Vec::new())
+    }
+
+    pub(super) fn streaming(path: &Path) -> Result<Self> {
+        Ok(DataSink::Streaming {
+            file: File::create(path)?,
+            path: path.to_path_buf(),
+            flushed_len: 0,
+            pending: Vec::new(),
+            peak_pending_bytes: 0,
+        })
+    }
+
+    /// Logical length of everything written so far, whether or not it has hit disk yet.
+    pub(super) fn len(&self) -> usize {
+        match self {
+            DataSink::Buffered(buf) => buf.len(),
+            DataSink::Streaming {
+                flushed_len,
+                pending,
+                ..
+            } => flushed_len + pending.len(),
+        }
+    }
+
+    pub(super) fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        match self {
+            DataSink::Buffered(buf) => {
+                buf.extend_from_slice(bytes);
+                Ok(())
+            }
+            DataSink::Streaming {
+                file,
+                flushed_len,
+                pending,
+                peak_pending_bytes,
+                ..
+            } => {
+                pending.extend_from_slice(bytes);
+                *peak_pending_bytes = (*peak_pending_bytes).max(pending.len());
+                if pending.len() >= STREAM_FLUSH_THRESHOLD {
+                    file.write_all(pending)?;
+                    *flushed_len += pending.len();
+                    pending.clear();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// High-water mark of bytes held in memory at once. For `Buffered` this is the whole table;
+    /// for `Streaming` it's bounded by the flush threshold plus the size of one write. Exposed so
+    /// tests can assert streaming
+    /// actually keeps memory use flat.
+    #[cfg(test)]
+    pub(super) fn peak_buffered_bytes(&self) -> usize {
+        match self {
+            DataSink::Buffered(buf) => buf.len(),
+            DataSink::Streaming {
+                peak_pending_bytes, ..
+            } => *peak_pending_bytes,
+        }
+    }
+
+    /// Flush any remaining bytes and hand back a `FileObject` for the finished table, backed
+    /// according to `optimize_for`. `path` is only used by the buffered variant; the streaming
+    /// variant already knows where it's writing and errors if `path` names somewhere else.
+    pub(super) fn finalize(self, path: &Path, optimize_for: OptimizeFor) -> Result<FileObject> {
+        match self {
+            DataSink::Buffered(buf) => FileObject::create(path, buf, optimize_for),
+            DataSink::Streaming {
+                mut file,
+                path: stream_path,
+                pending,
+                ..
+            } => {
+                anyhow::ensure!(
+                    path == stream_path.as_path(),
+                    "streaming builder was created for {}, not {}",
+                    stream_path.display(),
+                    path.display()
+                );
+                file.write_all(&pending)?;
+                file.sync_all()?;
+                FileObject::open(&stream_path, optimize_for)
+            }
+        }
+    }
+}